@@ -1,11 +1,13 @@
 //! Performs a simple taint analysis, to find all live ranges that are reftyped.
 
 use crate::data_structures::{
-    InstPoint, Map, MoveInfo, MoveInfoElem, RangeFrag, RangeFragIx, RangeId, RealRange,
-    RealRangeIx, Reg, RegClass, RegToRangesMaps, TypedIxVec, VirtualRange, VirtualRangeIx,
-    VirtualReg,
+    AnalysisError, InstIx, InstPoint, Map, MoveInfo, MoveInfoElem, RangeFrag, RangeFragIx,
+    RangeId, RealRange, RealRangeIx, Reg, RegClass, RegToRangesMaps, TypedIxVec, VirtualRange,
+    VirtualRangeIx, VirtualReg,
 };
+use crate::linear_scan::{IntId, Intervals};
 use crate::sparse_set::{SparseSet, SparseSetU};
+use crate::union_find::UnionFind;
 use std::{fmt, hash::Hash};
 
 use log::debug;
@@ -16,9 +18,14 @@ pub(crate) trait ReftypeAnalysis {
     /// An unified representation of a range, for both virtual and real ranges.
     type RangeId: Eq + Hash + Copy + fmt::Debug;
 
-    /// Find the RangeId related to `reg` and containing `pt`. May panic if the point isn't
-    /// actually present in any range of the given register.
-    fn find_range_id_for_reg(&self, pt: InstPoint, reg: Reg) -> Self::RangeId;
+    /// Find the RangeId related to `reg` and containing `pt`. Returns
+    /// `AnalysisError::ReftypeMoveSpansUnknownRange` if the point isn't actually present in any
+    /// range of the given register, which can happen on malformed client input.
+    fn find_range_id_for_reg(
+        &self,
+        pt: InstPoint,
+        reg: Reg,
+    ) -> Result<Self::RangeId, AnalysisError>;
 
     /// Add all the ranges associated to this vreg into the set of reftyped ranges.
     fn insert_reffy_ranges(&self, vreg: VirtualReg, set: &mut SparseSet<Self::RangeId>);
@@ -27,6 +34,17 @@ pub(crate) trait ReftypeAnalysis {
     fn mark_reffy(&mut self, range_id: &Self::RangeId);
 }
 
+/// A "ref-forwarding" instruction, as supplied by the client: an instruction, other than a
+/// plain move, whose `def` is reffy iff at least one of `srcs` is.  This covers things like
+/// `select`/`phi`/pointer-adjusting ops, where refness flows conditionally from a chosen
+/// operand rather than unconditionally as with a move.  Unlike moves, this relation is
+/// one-directional: a reffy `def` doesn't imply that any particular source was reffy.
+pub(crate) struct RefForwardElem {
+    pub(crate) def: Reg,
+    pub(crate) iix: InstIx,
+    pub(crate) srcs: SmallVec<[Reg; 4]>,
+}
+
 /// Implementation of the reftype analysis for the backtracking algorithm.
 struct BacktrackingReftypeAnalysis<'a> {
     rlr_env: &'a mut TypedIxVec<RealRangeIx, RealRange>,
@@ -39,24 +57,28 @@ impl<'a> ReftypeAnalysis for BacktrackingReftypeAnalysis<'a> {
     type RangeId = RangeId;
 
     #[inline(always)]
-    fn find_range_id_for_reg(&self, pt: InstPoint, reg: Reg) -> Self::RangeId {
+    fn find_range_id_for_reg(
+        &self,
+        pt: InstPoint,
+        reg: Reg,
+    ) -> Result<Self::RangeId, AnalysisError> {
         if reg.is_real() {
             for &rlrix in &self.reg_to_ranges_maps.rreg_to_rlrs_map[reg.get_index() as usize] {
                 if self.rlr_env[rlrix]
                     .sorted_frags
                     .contains_pt(self.frag_env, pt)
                 {
-                    return RangeId::new_real(rlrix);
+                    return Ok(RangeId::new_real(rlrix));
                 }
             }
         } else {
             for &vlrix in &self.reg_to_ranges_maps.vreg_to_vlrs_map[reg.get_index() as usize] {
                 if self.vlr_env[vlrix].sorted_frags.contains_pt(pt) {
-                    return RangeId::new_virtual(vlrix);
+                    return Ok(RangeId::new_virtual(vlrix));
                 }
             }
         }
-        panic!("do_reftypes_analysis::find_range_for_reg: can't find range");
+        Err(AnalysisError::ReftypeMoveSpansUnknownRange { reg, at: pt })
     }
 
     #[inline(always)]
@@ -83,6 +105,136 @@ impl<'a> ReftypeAnalysis for BacktrackingReftypeAnalysis<'a> {
     }
 }
 
+/// Implementation of the reftype analysis for the linear-scan algorithm.  Looks up ranges
+/// through `Intervals`'s per-reg maps rather than scanning `fixeds`/`virtuals`, the same way
+/// `BacktrackingReftypeAnalysis` uses `reg_to_ranges_maps` instead of scanning `rlr_env`/
+/// `vlr_env`.
+struct LinearScanReftypeAnalysis<'a> {
+    intervals: &'a mut Intervals,
+}
+
+impl<'a> ReftypeAnalysis for LinearScanReftypeAnalysis<'a> {
+    type RangeId = IntId;
+
+    #[inline(always)]
+    fn find_range_id_for_reg(
+        &self,
+        pt: InstPoint,
+        reg: Reg,
+    ) -> Result<Self::RangeId, AnalysisError> {
+        if reg.is_real() {
+            for &ix in &self.intervals.rreg_to_fixeds_map[reg.get_index() as usize] {
+                if self.intervals.fixeds[ix as usize].sorted_frags.contains_pt(pt) {
+                    return Ok(IntId::new_fixed(ix));
+                }
+            }
+        } else {
+            for &ix in &self.intervals.vreg_to_virtuals_map[reg.get_index() as usize] {
+                if self.intervals.virtuals[ix as usize].sorted_frags.contains_pt(pt) {
+                    return Ok(IntId::new_virtual(ix));
+                }
+            }
+        }
+        Err(AnalysisError::ReftypeMoveSpansUnknownRange { reg, at: pt })
+    }
+
+    #[inline(always)]
+    fn mark_reffy(&mut self, range: &Self::RangeId) {
+        if range.is_fixed() {
+            let fixed = &mut self.intervals.fixeds[range.to_fixed() as usize];
+            debug_assert!(!fixed.is_ref);
+            debug!(" -> fixed interval {:?} is reffy", range);
+            fixed.is_ref = true;
+        } else {
+            let virt = &mut self.intervals.virtuals[range.to_virtual() as usize];
+            debug_assert!(!virt.is_ref);
+            debug!(" -> virtual interval {:?} is reffy", range);
+            virt.is_ref = true;
+        }
+    }
+
+    #[inline(always)]
+    fn insert_reffy_ranges(&self, vreg: VirtualReg, set: &mut SparseSet<Self::RangeId>) {
+        for &ix in &self.intervals.vreg_to_virtuals_map[vreg.get_index() as usize] {
+            debug!(
+                "interval {:?} is reffy due to reffy vreg {:?}",
+                ix, vreg
+            );
+            set.insert(IntId::new_virtual(ix));
+        }
+    }
+}
+
+/// The reftyped ranges found live across a single requested safepoint, as computed by
+/// [`do_reftypes_analysis`] and [`do_reftypes_analysis_linear_scan`].  A GC client feeds this
+/// straight into its `StackmapRequestInfo`-style stackmap emission, to work out the spill-slot/
+/// register locations of the refs live at that point, without doing a second liveness walk.
+/// Generic over the id type so both allocators can report the same shape of result.
+pub(crate) struct SafepointReftypedRanges<Id> {
+    pub(crate) safepoint: InstIx,
+    pub(crate) ranges: Vec<Id>,
+}
+
+/// Given, for each range in allocation order, whether it's reftyped and whether it's live at
+/// the point of interest, returns the dense indices of the ranges that are both.  Split out of
+/// the per-safepoint loops in [`do_reftypes_analysis`] and [`do_reftypes_analysis_linear_scan`]
+/// so the filter itself can be unit-tested against plain bools, without needing real
+/// `RealRange`/`VirtualRange`/interval liveness data.
+fn live_reffy_range_ixs(is_ref_and_live: &[(bool, bool)]) -> Vec<u32> {
+    is_ref_and_live
+        .iter()
+        .enumerate()
+        .filter(|&(_, &(is_ref, live))| is_ref && live)
+        .map(|(ix, _)| ix as u32)
+        .collect()
+}
+
+/// Runs the reftype taint analysis for the linear-scan allocator, producing the same reffy-range
+/// annotations as [`do_reftypes_analysis`] for the same input, just expressed in terms of
+/// `IntId`-addressed intervals instead of `RealRange`/`VirtualRange`.
+pub(crate) fn do_reftypes_analysis_linear_scan(
+    intervals: &mut Intervals,
+    move_info: &MoveInfo,
+    ref_forwards: &[RefForwardElem],
+    reftype_class: RegClass,
+    reftyped_vregs: &Vec<VirtualReg>,
+    // Safepoints to report the reftyped-live set for, if any.
+    safepoints: &[InstIx],
+) -> Result<Vec<SafepointReftypedRanges<IntId>>, AnalysisError> {
+    let mut analysis = LinearScanReftypeAnalysis { intervals };
+    core_reftypes_analysis(
+        &mut analysis,
+        move_info,
+        ref_forwards,
+        reftype_class,
+        reftyped_vregs,
+    )?;
+
+    // ====== For each requested safepoint, collect the reftyped intervals live across it ======
+    Ok(safepoints
+        .iter()
+        .map(|&safepoint| {
+            let pt = InstPoint::new_use(safepoint);
+            let fixed_flags: Vec<(bool, bool)> = analysis
+                .intervals
+                .fixeds
+                .iter()
+                .map(|fixed| (fixed.is_ref, fixed.sorted_frags.contains_pt(pt)))
+                .collect();
+            let virt_flags: Vec<(bool, bool)> = analysis
+                .intervals
+                .virtuals
+                .iter()
+                .map(|virt| (virt.is_ref, virt.sorted_frags.contains_pt(pt)))
+                .collect();
+            let mut ranges = Vec::new();
+            ranges.extend(live_reffy_range_ixs(&fixed_flags).into_iter().map(IntId::new_fixed));
+            ranges.extend(live_reffy_range_ixs(&virt_flags).into_iter().map(IntId::new_virtual));
+            SafepointReftypedRanges { safepoint, ranges }
+        })
+        .collect())
+}
+
 pub(crate) fn do_reftypes_analysis(
     // From dataflow/liveness analysis.  Modified by setting their is_ref bit.
     rlr_env: &mut TypedIxVec<RealRangeIx, RealRange>,
@@ -92,72 +244,139 @@ pub(crate) fn do_reftypes_analysis(
     reg_to_ranges_maps: &RegToRangesMaps,
     move_info: &MoveInfo,
     // As supplied by the client
+    ref_forwards: &[RefForwardElem],
     reftype_class: RegClass,
     reftyped_vregs: &Vec<VirtualReg>,
-) {
+    // Safepoints to report the reftyped-live set for, if any.
+    safepoints: &[InstIx],
+) -> Result<Vec<SafepointReftypedRanges<RangeId>>, AnalysisError> {
     let mut analysis = BacktrackingReftypeAnalysis {
         rlr_env,
         vlr_env,
         frag_env,
         reg_to_ranges_maps,
     };
-    core_reftypes_analysis(&mut analysis, move_info, reftype_class, reftyped_vregs);
+    core_reftypes_analysis(
+        &mut analysis,
+        move_info,
+        ref_forwards,
+        reftype_class,
+        reftyped_vregs,
+    )?;
+
+    // ====== For each requested safepoint, collect the reftyped ranges live across it ======
+    Ok(safepoints
+        .iter()
+        .map(|&safepoint| {
+            let pt = InstPoint::new_use(safepoint);
+            let rlr_flags: Vec<(bool, bool)> = analysis
+                .rlr_env
+                .iter()
+                .map(|rlr| (rlr.is_ref, rlr.sorted_frags.contains_pt(analysis.frag_env, pt)))
+                .collect();
+            let vlr_flags: Vec<(bool, bool)> = analysis
+                .vlr_env
+                .iter()
+                .map(|vlr| (vlr.is_ref, vlr.sorted_frags.contains_pt(pt)))
+                .collect();
+            let mut ranges = Vec::new();
+            ranges.extend(
+                live_reffy_range_ixs(&rlr_flags)
+                    .into_iter()
+                    .map(|ix| RangeId::new_real(RealRangeIx::new(ix))),
+            );
+            ranges.extend(
+                live_reffy_range_ixs(&vlr_flags)
+                    .into_iter()
+                    .map(|ix| RangeId::new_virtual(VirtualRangeIx::new(ix))),
+            );
+            SafepointReftypedRanges { safepoint, ranges }
+        })
+        .collect())
+}
+
+/// Assigns `range` a dense `u32` index, allocating a fresh one the first time it's seen.
+fn range_dense_index<RangeId: Eq + Hash + Copy>(
+    range_to_ix: &mut Map<RangeId, u32>,
+    ix_to_range: &mut Vec<RangeId>,
+    range: RangeId,
+) -> u32 {
+    if let Some(&ix) = range_to_ix.get(&range) {
+        return ix;
+    }
+    let ix = ix_to_range.len() as u32;
+    ix_to_range.push(range);
+    range_to_ix.insert(range, ix);
+    ix
 }
 
 pub(crate) fn core_reftypes_analysis<RA: ReftypeAnalysis>(
     analysis: &mut RA,
     move_info: &MoveInfo,
     // As supplied by the client
+    ref_forwards: &[RefForwardElem],
     reftype_class: RegClass,
     reftyped_vregs: &Vec<VirtualReg>,
-) {
+) -> Result<(), AnalysisError> {
     // The game here is: starting with `reftyped_vregs`, find *all* the VirtualRanges and
-    // RealRanges to which refness can flow, via instructions which the client's `is_move`
-    // function considers to be moves.
-
-    // This is done in three stages:
-    //
-    // (1) Create a mapping from source (virtual or real) ranges to sets of destination ranges.
-    //     We have `move_info`, which tells us which (virtual or real) regs are connected by
-    //     moves.  However, that's not directly useful -- we need to know which *ranges* are
-    //     connected by moves.  `move_info` as supplied helpfully indicates both source and
-    //     destination regs and ranges, so we can simply use that.
+    // RealRanges to which refness can flow, via moves, and via any other "ref-forwarding"
+    // instructions the client tells us about (`select`/`phi`/pointer-adjusting ops, where a
+    // def is reffy iff at least one chosen operand is).
     //
-    // (2) Similarly, convert `reftyped_vregs` into a set of reftyped ranges by consulting
-    //     `reg_to_ranges_maps`.
-    //
-    // (3) Compute the transitive closure of (1) starting from the ranges in (2).  This is done
-    //     by a depth first search of the graph implied by (1).
+    // A move `d := s` makes `s` and `d` the same value, so a move edge is really an
+    // equivalence, not an implication: if either end of it is reffy, so is the other.  We fold
+    // each move's endpoints into one union-find component rather than a directed edge, which
+    // gets us backward propagation (reffy `d` => reffy `s`) for free, instead of only forward
+    // as a plain successor-graph walk would give us.  Ref-forwards don't have that symmetry --
+    // a reffy def says nothing about which source was reffy -- so those become directed edges
+    // between components, and we chase them to a fixpoint with a worklist, same as any other
+    // dataflow problem.
+
+    // ====== Assign every range mentioned by a move or a ref-forward a dense index ======
+    let mut range_to_ix = Map::<RA::RangeId, u32>::default();
+    let mut ix_to_range = Vec::<RA::RangeId>::new();
+    let mut move_edges = Vec::<(u32, u32)>::new();
 
-    // ====== Compute (1) above ======
-    // Each entry in `succ` maps from `src` to a `SparseSet<dsts>`, so to speak.  That is, for
-    // `d1`, `d2`, etc, in `dsts`, the function contains moves `d1 := src`, `d2 := src`, etc.
-    let mut succ = Map::<RA::RangeId, SparseSetU<[RA::RangeId; 4]>>::default();
     for &MoveInfoElem { dst, src, iix, .. } in move_info.iter() {
         // Don't waste time processing moves which can't possibly be of reftyped values.
         debug_assert!(dst.get_class() == src.get_class());
         if dst.get_class() != reftype_class {
             continue;
         }
-        let src_range = analysis.find_range_id_for_reg(InstPoint::new_use(iix), src);
-        let dst_range = analysis.find_range_id_for_reg(InstPoint::new_def(iix), dst);
+        let src_range = analysis.find_range_id_for_reg(InstPoint::new_use(iix), src)?;
+        let dst_range = analysis.find_range_id_for_reg(InstPoint::new_def(iix), dst)?;
         debug!(
             "move from {:?} (range {:?}) to {:?} (range {:?}) at inst {:?}",
             src, src_range, dst, dst_range, iix
         );
-        match succ.get_mut(&src_range) {
-            Some(dst_ranges) => dst_ranges.insert(dst_range),
-            None => {
-                // Re `; 4`: we expect most copies copy a register to only a few destinations.
-                let mut dst_ranges = SparseSetU::<[RA::RangeId; 4]>::empty();
-                dst_ranges.insert(dst_range);
-                let r = succ.insert(src_range, dst_ranges);
-                assert!(r.is_none());
+        let src_ix = range_dense_index(&mut range_to_ix, &mut ix_to_range, src_range);
+        let dst_ix = range_dense_index(&mut range_to_ix, &mut ix_to_range, dst_range);
+        move_edges.push((src_ix, dst_ix));
+    }
+
+    // ====== Record the directed edges implied by the ref-forwarding instructions ======
+    let mut fwd_edges = Vec::<(u32, u32)>::new();
+    for RefForwardElem { def, iix, srcs } in ref_forwards {
+        if def.get_class() != reftype_class {
+            continue;
+        }
+        let def_range = analysis.find_range_id_for_reg(InstPoint::new_def(*iix), *def)?;
+        let def_ix = range_dense_index(&mut range_to_ix, &mut ix_to_range, def_range);
+        for src in srcs {
+            if src.get_class() != reftype_class {
+                continue;
             }
+            let src_range = analysis.find_range_id_for_reg(InstPoint::new_use(*iix), *src)?;
+            debug!(
+                "ref-forward from {:?} (range {:?}) to {:?} (range {:?}) at inst {:?}",
+                src, src_range, def, def_range, iix
+            );
+            let src_ix = range_dense_index(&mut range_to_ix, &mut ix_to_range, src_range);
+            fwd_edges.push((src_ix, def_ix));
         }
     }
 
-    // ====== Compute (2) above ======
+    // ====== Convert `reftyped_vregs` into a set of reftyped ranges ======
     let mut reftyped_ranges = SparseSet::<RA::RangeId>::empty();
     for vreg in reftyped_vregs {
         // If this fails, the client has been telling is that some virtual reg is reftyped, yet
@@ -166,29 +385,159 @@ pub(crate) fn core_reftypes_analysis<RA: ReftypeAnalysis>(
         debug_assert!(vreg.get_class() == reftype_class);
         analysis.insert_reffy_ranges(*vreg, &mut reftyped_ranges);
     }
+    let seed_ixs: SmallVec<[u32; 16]> = reftyped_ranges
+        .iter()
+        .map(|range| range_dense_index(&mut range_to_ix, &mut ix_to_range, *range))
+        .collect();
+
+    let (uf, reffy_roots) =
+        propagate_reffy_components(ix_to_range.len() as u32, &move_edges, &fwd_edges, &seed_ixs);
+
+    // ====== Mark every range whose connected component ended up reffy ======
+    for (ix, range) in ix_to_range.iter().enumerate() {
+        if reffy_roots.contains(uf.find(ix as u32)) {
+            analysis.mark_reffy(range);
+        }
+    }
+
+    Ok(())
+}
+
+/// The pure graph half of [`core_reftypes_analysis`]: given `num_ranges` dense range indices,
+/// the move edges (folded into union-find components, since a move is an equivalence) and
+/// ref-forward edges (directed, between components) between them, and the indices seeded as
+/// reffy, works out which components end up reffy.  Split out from `core_reftypes_analysis` so
+/// the propagation logic can be unit-tested on plain integers, without needing a
+/// `ReftypeAnalysis` impl or any of the surrounding `Reg`/`MoveInfo` machinery.
+fn propagate_reffy_components(
+    num_ranges: u32,
+    move_edges: &[(u32, u32)],
+    fwd_edges: &[(u32, u32)],
+    seed_ixs: &[u32],
+) -> (UnionFind<u32>, SparseSetU<[u32; 16]>) {
+    // ====== Union every range connected by a move into the same component ======
+    let mut uf = UnionFind::<u32>::new(num_ranges);
+    for &(src_ix, dst_ix) in move_edges {
+        uf.union(src_ix, dst_ix);
+    }
+
+    // ====== Build the component-level successor graph implied by the ref-forwards ======
+    let mut succ = Map::<u32, SparseSetU<[u32; 4]>>::default();
+    for &(src_ix, dst_ix) in fwd_edges {
+        let (src_root, dst_root) = (uf.find(src_ix), uf.find(dst_ix));
+        if src_root == dst_root {
+            continue;
+        }
+        match succ.get_mut(&src_root) {
+            Some(dst_roots) => dst_roots.insert(dst_root),
+            None => {
+                let mut dst_roots = SparseSetU::<[u32; 4]>::empty();
+                dst_roots.insert(dst_root);
+                succ.insert(src_root, dst_roots);
+            }
+        }
+    }
 
-    // ====== Compute (3) above ======
-    // Almost all chains of copies will be less than 64 long, I would guess.
-    let mut stack = SmallVec::<[RA::RangeId; 64]>::new();
-    let mut visited = reftyped_ranges.clone();
-    for start_point_range in reftyped_ranges.iter() {
-        // Perform DFS from `start_point_range`.
-        stack.clear();
-        stack.push(*start_point_range);
-        while let Some(src_range) = stack.pop() {
-            visited.insert(src_range);
-            if let Some(dst_ranges) = succ.get(&src_range) {
-                for dst_range in dst_ranges.iter() {
-                    if !visited.contains(*dst_range) {
-                        stack.push(*dst_range);
+    // ====== Worklist fixpoint: propagate refness along the ref-forwarding graph ======
+    // Almost all chains of ref-forwards will be less than 64 long, I would guess.
+    let mut stack = SmallVec::<[u32; 64]>::new();
+    let mut visited_roots = SparseSetU::<[u32; 16]>::empty();
+    for &ix in seed_ixs {
+        visited_roots.insert(uf.find(ix));
+    }
+    for &ix in seed_ixs {
+        stack.push(uf.find(ix));
+        while let Some(src_root) = stack.pop() {
+            visited_roots.insert(src_root);
+            if let Some(dst_roots) = succ.get(&src_root) {
+                for dst_root in dst_roots.iter() {
+                    if !visited_roots.contains(*dst_root) {
+                        stack.push(*dst_root);
                     }
                 }
             }
         }
     }
 
-    // Finally, annotate the results of the analysis.
-    for range in visited.iter() {
-        analysis.mark_reffy(range);
+    (uf, visited_roots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn safepoint_filter_keeps_only_ranges_both_reffy_and_live() {
+        // range 0: reffy, live across the safepoint -- should be reported.
+        // range 1: reffy, but not live across the safepoint -- must be dropped.
+        // range 2: live across the safepoint, but not reffy -- must be dropped.
+        // range 3: neither -- must be dropped.
+        let is_ref_and_live = [(true, true), (true, false), (false, true), (false, false)];
+        assert_eq!(live_reffy_range_ixs(&is_ref_and_live), vec![0]);
+    }
+
+    #[test]
+    fn move_propagates_refness_backward_to_the_source() {
+        // range 1 := range 0 (a move).  Only the destination (1) is seeded as reffy; the move
+        // is an equivalence, so the source (0) must end up reffy too.
+        let (uf, reffy_roots) = propagate_reffy_components(2, &[(0, 1)], &[], &[1]);
+        assert!(reffy_roots.contains(uf.find(0)));
+        assert!(reffy_roots.contains(uf.find(1)));
+    }
+
+    #[test]
+    fn ref_forward_propagates_across_multiple_hops_but_not_into_unrelated_components() {
+        // b := a (move, ranges 0 := 1); c ref-forwards from b (0 -> 2); d ref-forwards from c
+        // (2 -> 3).  Seeding b (0) as reffy should reach a, c and d, but must not bleed into an
+        // unrelated range e (4).
+        let (uf, reffy_roots) =
+            propagate_reffy_components(5, &[(1, 0)], &[(0, 2), (2, 3)], &[0]);
+        assert!(reffy_roots.contains(uf.find(0))); // b
+        assert!(reffy_roots.contains(uf.find(1))); // a
+        assert!(reffy_roots.contains(uf.find(2))); // c
+        assert!(reffy_roots.contains(uf.find(3))); // d
+        assert!(!reffy_roots.contains(uf.find(4))); // e, unrelated
+    }
+
+    #[test]
+    fn unresolvable_ref_forward_target_returns_analysis_error() {
+        // A mock analysis that can never find a range for a reg, as if the client handed us a
+        // ref-forward mentioning a point outside of any range/interval for that reg.
+        struct NoRangesAnalysis;
+        impl ReftypeAnalysis for NoRangesAnalysis {
+            type RangeId = u32;
+            fn find_range_id_for_reg(
+                &self,
+                pt: InstPoint,
+                reg: Reg,
+            ) -> Result<Self::RangeId, AnalysisError> {
+                Err(AnalysisError::ReftypeMoveSpansUnknownRange { reg, at: pt })
+            }
+            fn insert_reffy_ranges(&self, _vreg: VirtualReg, _set: &mut SparseSet<Self::RangeId>) {}
+            fn mark_reffy(&mut self, _range_id: &Self::RangeId) {}
+        }
+
+        let def = Reg::new_virtual(VirtualReg::new(0, RegClass::I64));
+        let src = Reg::new_virtual(VirtualReg::new(1, RegClass::I64));
+        let ref_forwards = vec![RefForwardElem {
+            def,
+            iix: InstIx::new(0),
+            srcs: smallvec::smallvec![src],
+        }];
+        let move_info: MoveInfo = MoveInfo::new();
+        let reftyped_vregs = Vec::new();
+
+        let mut analysis = NoRangesAnalysis;
+        let result = core_reftypes_analysis(
+            &mut analysis,
+            &move_info,
+            &ref_forwards,
+            RegClass::I64,
+            &reftyped_vregs,
+        );
+        assert!(matches!(
+            result,
+            Err(AnalysisError::ReftypeMoveSpansUnknownRange { .. })
+        ));
     }
 }
@@ -0,0 +1,37 @@
+//! Backtracking register allocator: driver glue for the reftype analysis.
+
+use crate::analysis_reftypes::{self, RefForwardElem, SafepointReftypedRanges};
+use crate::data_structures::{
+    AnalysisError, InstIx, MoveInfo, RangeFrag, RangeFragIx, RangeId, RealRange, RealRangeIx,
+    RegClass, RegToRangesMaps, TypedIxVec, VirtualRange, VirtualRangeIx, VirtualReg,
+};
+
+/// Runs the reftype taint analysis as part of the backtracking allocator's main pipeline, once
+/// dataflow/liveness has populated `rlr_env`/`vlr_env`.  `ref_forwards` carries the client's
+/// non-move ref-forwarding instructions.  `safepoints` are the points the client wants a
+/// reftyped-live set reported for, for stackmap generation.  Fails with
+/// `AnalysisError::ReftypeMoveSpansUnknownRange` if `move_info`/`ref_forwards` mentions a point
+/// the liveness analysis didn't actually cover for that reg.
+pub(crate) fn run_reftype_analysis(
+    rlr_env: &mut TypedIxVec<RealRangeIx, RealRange>,
+    vlr_env: &mut TypedIxVec<VirtualRangeIx, VirtualRange>,
+    frag_env: &TypedIxVec<RangeFragIx, RangeFrag>,
+    reg_to_ranges_maps: &RegToRangesMaps,
+    move_info: &MoveInfo,
+    ref_forwards: &[RefForwardElem],
+    reftype_class: RegClass,
+    reftyped_vregs: &Vec<VirtualReg>,
+    safepoints: &[InstIx],
+) -> Result<Vec<SafepointReftypedRanges<RangeId>>, AnalysisError> {
+    analysis_reftypes::do_reftypes_analysis(
+        rlr_env,
+        vlr_env,
+        frag_env,
+        reg_to_ranges_maps,
+        move_info,
+        ref_forwards,
+        reftype_class,
+        reftyped_vregs,
+        safepoints,
+    )
+}
@@ -0,0 +1,17 @@
+//! Core data structures shared by the analysis passes.
+
+/// Errors that the analysis passes can report back to the client, instead of panicking on
+/// malformed input.
+pub(crate) enum AnalysisError {
+    /// A critical edge was found in the control flow graph, which the client was supposed to
+    /// have removed before handing the function to the allocator.
+    CriticalEdge { from: InstIx, to: InstIx },
+
+    /// A virtual reg is live in at the entry block, which isn't allowed.
+    EntryLiveinValues(Vec<VirtualReg>),
+
+    /// A move (or ref-forwarding instruction) mentioned `reg` at `at`, but no range or
+    /// interval of `reg` actually covers that point.  This means the client handed the
+    /// allocator a `move_info`/ref-forwarding list that doesn't match its own liveness.
+    ReftypeMoveSpansUnknownRange { reg: Reg, at: InstPoint },
+}
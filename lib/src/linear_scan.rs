@@ -0,0 +1,118 @@
+//! Linear-scan register allocator: interval representation and driver glue.
+
+use crate::analysis_reftypes::{self, RefForwardElem, SafepointReftypedRanges};
+use crate::data_structures::{
+    AnalysisError, InstIx, InstPoint, MoveInfo, RangeFrag, RegClass, Reg, VirtualReg,
+};
+use smallvec::SmallVec;
+
+/// A dense index into either the fixed or the virtual interval vectors of [`Intervals`],
+/// playing the same real/virtual-tagged role for linear-scan as `RangeId` does for the
+/// backtracking allocator.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum IntId {
+    Fixed(u32),
+    Virtual(u32),
+}
+
+impl IntId {
+    #[inline(always)]
+    pub(crate) fn new_fixed(ix: u32) -> Self {
+        IntId::Fixed(ix)
+    }
+    #[inline(always)]
+    pub(crate) fn new_virtual(ix: u32) -> Self {
+        IntId::Virtual(ix)
+    }
+    #[inline(always)]
+    pub(crate) fn is_fixed(&self) -> bool {
+        matches!(self, IntId::Fixed(_))
+    }
+    #[inline(always)]
+    pub(crate) fn to_fixed(&self) -> u32 {
+        match self {
+            IntId::Fixed(ix) => *ix,
+            IntId::Virtual(_) => panic!("IntId::to_fixed on a virtual interval"),
+        }
+    }
+    #[inline(always)]
+    pub(crate) fn to_virtual(&self) -> u32 {
+        match self {
+            IntId::Virtual(ix) => *ix,
+            IntId::Fixed(_) => panic!("IntId::to_virtual on a fixed interval"),
+        }
+    }
+}
+
+/// The fragments a linear-scan interval is live over.  Kept sorted and non-overlapping, the
+/// same invariant `VirtualRange::sorted_frags` relies on in the backtracking allocator, so
+/// `contains_pt` can binary-search instead of scanning every fragment.
+pub(crate) struct SortedRangeFrags(pub(crate) Vec<RangeFrag>);
+
+impl SortedRangeFrags {
+    #[inline(always)]
+    pub(crate) fn contains_pt(&self, pt: InstPoint) -> bool {
+        self.0
+            .binary_search_by(|frag| {
+                if pt < frag.first {
+                    std::cmp::Ordering::Greater
+                } else if pt > frag.last {
+                    std::cmp::Ordering::Less
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+            .is_ok()
+    }
+}
+
+/// An interval pinned to a real register.
+pub(crate) struct FixedInterval {
+    pub(crate) reg: Reg,
+    pub(crate) sorted_frags: SortedRangeFrags,
+    pub(crate) is_ref: bool,
+}
+
+/// An interval carrying a virtual register.
+pub(crate) struct VirtualInterval {
+    pub(crate) vreg: VirtualReg,
+    pub(crate) sorted_frags: SortedRangeFrags,
+    pub(crate) is_ref: bool,
+}
+
+/// All the intervals built by the linear-scan liveness pass, addressed uniformly by `IntId`.
+pub(crate) struct Intervals {
+    pub(crate) fixeds: Vec<FixedInterval>,
+    pub(crate) virtuals: Vec<VirtualInterval>,
+    /// Dense index from a real reg's index to the `fixeds` that carry it, mirroring
+    /// `RegToRangesMaps::rreg_to_rlrs_map` for the backtracking allocator, so
+    /// `find_range_id_for_reg` only has to look at the handful of intervals for that one reg
+    /// instead of scanning all of `fixeds`.
+    pub(crate) rreg_to_fixeds_map: Vec<SmallVec<[u32; 4]>>,
+    /// Same, but from a virtual reg's index to the `virtuals` that carry it.
+    pub(crate) vreg_to_virtuals_map: Vec<SmallVec<[u32; 4]>>,
+}
+
+/// Runs the reftype taint analysis over the intervals built by linear-scan liveness.  Called
+/// right after those intervals are constructed, so that linear-scan produces the same
+/// reffy-range annotations as the backtracking allocator's `do_reftypes_analysis` for the
+/// same input, including a reftyped-live set for each of `safepoints`, for stackmap generation.
+/// Fails with `AnalysisError::ReftypeMoveSpansUnknownRange` if `move_info`/`ref_forwards`
+/// mentions a point the liveness analysis didn't actually cover for that reg.
+pub(crate) fn compute_reftyped_intervals(
+    intervals: &mut Intervals,
+    move_info: &MoveInfo,
+    ref_forwards: &[RefForwardElem],
+    reftype_class: RegClass,
+    reftyped_vregs: &Vec<VirtualReg>,
+    safepoints: &[InstIx],
+) -> Result<Vec<SafepointReftypedRanges<IntId>>, AnalysisError> {
+    analysis_reftypes::do_reftypes_analysis_linear_scan(
+        intervals,
+        move_info,
+        ref_forwards,
+        reftype_class,
+        reftyped_vregs,
+        safepoints,
+    )
+}